@@ -1,54 +1,124 @@
 use std::collections::HashMap;
-use crate::precision;
+use crate::transaction_type::TransactionType;
+use crate::LedgerError;
 
 
+/// Controls which transactions a client is permitted to dispute. Disputing a
+/// withdrawal pushes `held` up while driving `available` negative, so by
+/// default only deposits are disputable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisputePolicy {
+    DepositsOnly,
+    DepositsAndWithdrawals,
+}
+
+/// Runtime configuration for the ledger. Held by each [`ClientAccountState`]
+/// so dispute handling can consult the active policy.
+#[derive(Debug, Clone, Copy)]
+pub struct LedgerConfig {
+    pub dispute_policy: DisputePolicy,
+}
+
+impl Default for LedgerConfig {
+    fn default() -> Self {
+        LedgerConfig {
+            dispute_policy: DisputePolicy::DepositsOnly,
+        }
+    }
+}
+
+/// The lifecycle a single transaction can move through. Only a handful of
+/// transitions are legal: `Processed -> Disputed` (dispute),
+/// `Disputed -> Resolved` (resolve) and `Disputed -> ChargedBack` (chargeback).
+/// `ChargedBack` is terminal.
+#[derive(Debug, PartialEq)]
+pub enum TxState {
+    Processed,
+    Disputed,
+    Resolved,
+    ChargedBack,
+}
+
 #[derive(Debug)]
 pub struct TxMeta {
-    amount: f32,
-    under_dispute: bool,
+    amount: i64,
+    kind: TransactionType,
+    state: TxState,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ClientAccountState {
-    pub available: f32,
-    pub held: f32,
+    pub client: u16,
+    pub available: i64,
+    pub held: i64,
     pub locked: bool,
     pub txs: HashMap<u32, TxMeta>,
+    pub config: LedgerConfig,
 }
 
 impl ClientAccountState {
+    /// Build an account state owned by `client` with an explicit ledger configuration.
+    pub fn new(client: u16, config: LedgerConfig) -> Self {
+        ClientAccountState {
+            client,
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Reject a staged `(available, held)` pair that would break the ledger
+    /// invariants `held >= 0` and `total >= 0`, leaving the account untouched.
+    fn check_invariants(available: i64, held: i64) -> Result<(), LedgerError> {
+        let total = available.checked_add(held).ok_or(LedgerError::NotEnoughFunds)?;
+        if held < 0 || total < 0 {
+            return Err(LedgerError::NotEnoughFunds);
+        }
+        Ok(())
+    }
+
     /// A deposit is a credit to the client's asset account,
-    /// meaning it should increase the available and total funds of the client account
-    pub fn deposit(&mut self, tx: u32, amount: f32) -> Result<(), String> {
-        let rounded_amount = precision::convert_precision(amount);
-        if rounded_amount > 0.0000 {
-            self.available += rounded_amount;
-            self.txs.insert(tx, TxMeta {
-                amount: rounded_amount,
-                under_dispute: false,
-            });
-            return Ok(());
+    /// meaning it should increase the available and total funds of the client account.
+    /// On success returns the signed effect on total issuance (`+amount`).
+    pub fn deposit(&mut self, tx: u32, amount: i64) -> Result<i64, LedgerError> {
+        if self.locked {
+            return Err(LedgerError::FrozenAccount);
+        }
+        if amount <= 0 {
+            return Err(LedgerError::NonPositiveAmount);
         }
-        Err(String::from(format!("Transaction {} failed: must be positive non-zero amount", tx)))
+        let available = self.available.checked_add(amount).ok_or(LedgerError::NotEnoughFunds)?;
+        Self::check_invariants(available, self.held)?;
+        self.available = available;
+        self.txs.insert(tx, TxMeta {
+            amount,
+            kind: TransactionType::DEPOSIT,
+            state: TxState::Processed,
+        });
+        Ok(amount)
     }
 
     /// A withdraw is a debit to the client's asset account,
     /// meaning it should decrease the available and total funds of the client account
     /// If a client does not have sufficient available funds the withdrawal should fail and the total amount of funds should not change
-    pub fn withdraw(&mut self, tx: u32, amount: f32) -> Result<(), String> {
-        let rounded_amount = precision::convert_precision(amount);
-        if rounded_amount <= 0.0000 {
-            return Err(String::from(format!("Transaction {} failed: must be positive non-zero amount", tx)));
+    pub fn withdraw(&mut self, tx: u32, amount: i64) -> Result<i64, LedgerError> {
+        if self.locked {
+            return Err(LedgerError::FrozenAccount);
+        }
+        if amount <= 0 {
+            return Err(LedgerError::NonPositiveAmount);
         }
         if amount <= self.available {
-            self.available -= rounded_amount;
+            let available = self.available.checked_sub(amount).ok_or(LedgerError::NotEnoughFunds)?;
+            Self::check_invariants(available, self.held)?;
+            self.available = available;
             self.txs.insert(tx, TxMeta {
-                amount: rounded_amount,
-                under_dispute: false,
+                amount,
+                kind: TransactionType::WITHDRAW,
+                state: TxState::Processed,
             });
-            return Ok(());
+            return Ok(-amount);
         }
-        Err(String::from(format!("Transaction {} failed: insufficient funds", tx)))
+        Err(LedgerError::NotEnoughFunds)
     }
 
     /// A dispute represents a client's claim that a transaction was erroneous and should be reversed.
@@ -58,15 +128,29 @@ impl ClientAccountState {
     /// while their total funds should remain the same.
     /// If the tx specified by the dispute doesn't exist,
     /// you can ignore it and assume this is an error on our partners side.
-    pub fn dispute(&mut self, tx: u32) -> Result<(), String> {
-        if let Some(tx_meta) = self.txs.get_mut(&tx) {
-            tx_meta.under_dispute = true;
+    pub fn dispute(&mut self, tx: u32) -> Result<i64, LedgerError> {
+        if self.locked {
+            return Err(LedgerError::FrozenAccount);
+        }
+        if let Some(tx_meta) = self.txs.get(&tx) {
+            if tx_meta.state != TxState::Processed {
+                return Err(LedgerError::AlreadyDisputed);
+            }
+            if tx_meta.kind == TransactionType::WITHDRAW
+                && self.config.dispute_policy == DisputePolicy::DepositsOnly {
+                return Err(LedgerError::DisputeNotAllowed);
+            }
             let disputed_funds = tx_meta.amount;
-            self.available -= disputed_funds;
-            self.held += disputed_funds;
-            return Ok(());
+            let available = self.available.checked_sub(disputed_funds).ok_or(LedgerError::NotEnoughFunds)?;
+            let held = self.held.checked_add(disputed_funds).ok_or(LedgerError::NotEnoughFunds)?;
+            Self::check_invariants(available, held)?;
+            self.available = available;
+            self.held = held;
+            self.txs.get_mut(&tx).unwrap().state = TxState::Disputed;
+            // A dispute only moves funds from available to held; issuance is unchanged.
+            return Ok(0);
         }
-        Err(String::from(format!("Dispute failed: transaction {} not found", tx)))
+        Err(LedgerError::UnknownTx { client: self.client, tx })
     }
 
     /// A resolve represents a resolution to a dispute,
@@ -78,17 +162,25 @@ impl ClientAccountState {
     /// If the tx specified doesn't exist,
     /// or the tx isn't under dispute,
     /// you can ignore the resolve and assume this is an error on our partner's side.
-    pub fn resolve(&mut self, tx: u32) -> Result<(), String> {
+    pub fn resolve(&mut self, tx: u32) -> Result<i64, LedgerError> {
+        if self.locked {
+            return Err(LedgerError::FrozenAccount);
+        }
         if let Some(tx_meta) = self.txs.get(&tx) {
-            if !tx_meta.under_dispute {
-                return Err(String::from("Invalid resolution: target transaction is not a dispute."));
+            if tx_meta.state != TxState::Disputed {
+                return Err(LedgerError::NotDisputed);
             }
             let disputed_funds = tx_meta.amount;
-            self.held -= disputed_funds;
-            self.available += disputed_funds;
-            return Ok(());
+            let held = self.held.checked_sub(disputed_funds).ok_or(LedgerError::NotEnoughFunds)?;
+            let available = self.available.checked_add(disputed_funds).ok_or(LedgerError::NotEnoughFunds)?;
+            Self::check_invariants(available, held)?;
+            self.held = held;
+            self.available = available;
+            self.txs.get_mut(&tx).unwrap().state = TxState::Resolved;
+            // A resolve only moves funds from held back to available; issuance is unchanged.
+            return Ok(0);
         }
-        Err(String::from("No such transaction found"))
+        Err(LedgerError::UnknownTx { client: self.client, tx })
     }
 
     /// A chargeback is the final state of a dispute and represents the client reversing a transaction.
@@ -98,135 +190,132 @@ impl ClientAccountState {
     /// If the tx specified doesn't exist,
     /// or the tx isn't under dispute,
     /// you can ignore the resolve and assume this is an error on our partner's side.
-    pub fn chargeback(&mut self, tx: u32) -> Result<(), String> {
-        if let Some(tx_meta) = self.txs.get_mut(&tx) {
-            if !tx_meta.under_dispute {
-                return Err(String::from("Invalid resolution: target transaction is not a dispute."));
+    pub fn chargeback(&mut self, tx: u32) -> Result<i64, LedgerError> {
+        if self.locked {
+            return Err(LedgerError::FrozenAccount);
+        }
+        if let Some(tx_meta) = self.txs.get(&tx) {
+            if tx_meta.state != TxState::Disputed {
+                return Err(LedgerError::NotDisputed);
             }
-            tx_meta.under_dispute = false;
             let disputed_funds = tx_meta.amount;
-            self.held -= disputed_funds;
+            let held = self.held.checked_sub(disputed_funds).ok_or(LedgerError::NotEnoughFunds)?;
+            Self::check_invariants(self.available, held)?;
+            self.txs.get_mut(&tx).unwrap().state = TxState::ChargedBack;
+            self.held = held;
             self.locked = true;
-            return Ok(());
+            // The charged-back funds leave the system entirely.
+            return Ok(-disputed_funds);
         }
-        Err(String::from("No such transaction found"))
-    }
-}
-
-impl Default for ClientAccountState {
-    fn default() -> Self {
-        return ClientAccountState {
-            available: 0.0000,
-            held: 0.0000,
-            locked: false,
-            txs: Default::default(),
-        };
+        Err(LedgerError::UnknownTx { client: self.client, tx })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::client_account_state::ClientAccountState;
+    use crate::client_account_state::{ClientAccountState, DisputePolicy, LedgerConfig, TxState};
+
+    fn permissive() -> ClientAccountState {
+        ClientAccountState::new(1, LedgerConfig {
+            dispute_policy: DisputePolicy::DepositsAndWithdrawals,
+        })
+    }
 
     #[test]
     fn test_deposit() {
         let mut state = ClientAccountState::default();
 
-        assert_eq!(state.available, 0.0000);
+        assert_eq!(state.available, 0);
 
         // Deposit zero amount should fail
-        let result = state.deposit(1, 0.000000000);
+        let result = state.deposit(1, 0);
         assert!(result.is_err());
 
         // Deposit negative amount should fail
-        let result = state.deposit(2, -0.000000001);
-        assert!(result.is_err());
-
-        // Deposit positive, non-zero amount with large precision should fail
-        let result = state.deposit(3, 0.0000000000001);
+        let result = state.deposit(2, -1);
         assert!(result.is_err());
 
         // Deposit positive non-zero amount should succeed
-        let result = state.deposit(4, 0.0001);
+        let result = state.deposit(4, 1);
         assert!(result.is_ok());
-        assert_eq!(state.available, 0.0001);
-        assert!(state.txs.get(&4).is_some());
+        assert_eq!(state.available, 1);
+        assert!(state.txs.contains_key(&4));
     }
 
     #[test]
     fn test_withdraw() {
         let mut state = ClientAccountState::default();
 
-        assert_eq!(state.available, 0.0000);
+        assert_eq!(state.available, 0);
 
         // Should fail due to insufficient funds
-        let result = state.withdraw(1, 0.00000000001);
+        let result = state.withdraw(1, 1);
         assert!(result.is_err());
-        assert_eq!(state.available, 0.0000);
-        assert!(state.txs.get(&1).is_none());
+        assert_eq!(state.available, 0);
+        assert!(!state.txs.contains_key(&1));
 
         // Deposit funds so we can attempt to withdraw
-        let _ = state.deposit(2, 60.0);
+        let _ = state.deposit(2, 600_000);
 
         // Should fail with negative amount
-        let result = state.withdraw(3, -30.0);
+        let result = state.withdraw(3, -300_000);
         assert!(result.is_err());
 
         // Should fail with zero amount
-        let result = state.withdraw(4, 0.00);
+        let result = state.withdraw(4, 0);
         assert!(result.is_err());
 
         // Should succeed after sufficient funds are available
-        let result = state.withdraw(5, 30.0);
+        let result = state.withdraw(5, 300_000);
         assert!(result.is_ok());
-        assert_eq!(state.available, 30.0);
-        assert!(state.txs.get(&2).is_some());
-        assert!(state.txs.get(&5).is_some());
+        assert_eq!(state.available, 300_000);
+        assert!(state.txs.contains_key(&2));
+        assert!(state.txs.contains_key(&5));
     }
 
     #[test]
     fn test_dispute() {
-        let mut state = ClientAccountState::default();
+        let mut state = permissive();
 
-        assert_eq!(state.available, 0.0);
-        assert_eq!(state.held, 0.0);
+        assert_eq!(state.available, 0);
+        assert_eq!(state.held, 0);
 
         // Disputing non-existent transaction should fail
         let result = state.dispute(1);
         assert!(result.is_err());
-        assert_eq!(state.available, 0.0);
-        assert_eq!(state.held, 0.0);
+        assert_eq!(state.available, 0);
+        assert_eq!(state.held, 0);
 
         // Dispute deposit
-        let _ = state.deposit(2, 60.0);
+        let _ = state.deposit(2, 600_000);
         let result = state.dispute(2);
         assert!(result.is_ok());
-        assert_eq!(state.available, 0.0);
-        assert_eq!(state.held, 60.0);
-        assert!(state.txs.get(&2).is_some());
-        assert!(state.txs.get(&2).unwrap().under_dispute);
+        assert_eq!(state.available, 0);
+        assert_eq!(state.held, 600_000);
+        assert!(state.txs.contains_key(&2));
+        assert_eq!(state.txs.get(&2).unwrap().state, TxState::Disputed);
 
         // Dispute withdrawal
-        let _ = state.deposit(3, 50.0);
-        let _ = state.withdraw(4, 50.0);
+        let _ = state.deposit(3, 500_000);
+        let _ = state.withdraw(4, 500_000);
         let result = state.dispute(4);
         assert!(result.is_ok());
-        assert_eq!(state.available, -50.0);
-        assert_eq!(state.held, 110.0);
-        assert!(state.txs.get(&2).is_some());
-        assert!(state.txs.get(&2).unwrap().under_dispute);
+        assert_eq!(state.available, -500_000);
+        assert_eq!(state.held, 1_100_000);
+        assert!(state.txs.contains_key(&4));
+        assert_eq!(state.txs.get(&4).unwrap().state, TxState::Disputed);
     }
 
     #[test]
     fn test_resolve() {
-        let mut state = ClientAccountState::default();
+        let mut state = permissive();
 
         // Resolving non-existent transaction should fail
         let result = state.resolve(1);
         assert!(result.is_err());
 
         // Resolving undisputed transaction should fail
-        let _ = state.deposit(2, 50.0);
+        let _ = state.deposit(2, 500_000);
         let result = state.resolve(2);
         assert!(result.is_err());
 
@@ -234,28 +323,28 @@ mod tests {
         let _ = state.dispute(2);
         let result = state.resolve(2);
         assert!(result.is_ok());
-        assert_eq!(state.available, 50.0);
-        assert_eq!(state.held, 0.0);
+        assert_eq!(state.available, 500_000);
+        assert_eq!(state.held, 0);
 
         // Resolve withdrawal
-        let _ = state.withdraw(3, 50.0);
+        let _ = state.withdraw(3, 500_000);
         let _ = state.dispute(3);
         let result = state.resolve(3);
         assert!(result.is_ok());
-        assert_eq!(state.available, 0.0);
-        assert_eq!(state.held, 0.0);
+        assert_eq!(state.available, 0);
+        assert_eq!(state.held, 0);
     }
 
     #[test]
     fn test_chargeback() {
-        let mut state = ClientAccountState::default();
+        let mut state = permissive();
 
         // Charge back non-existent transaction should fail
         let result = state.chargeback(1);
         assert!(result.is_err());
 
         // Charging back undisputed transaction should fail
-        let _ = state.deposit(2, 50.0);
+        let _ = state.deposit(2, 500_000);
         let result = state.chargeback(2);
         assert!(result.is_err());
 
@@ -263,19 +352,68 @@ mod tests {
         let _ = state.dispute(2);
         let result = state.chargeback(2);
         assert!(result.is_ok());
-        assert_eq!(state.available, 0.0);
-        assert_eq!(state.held, 0.0);
+        assert_eq!(state.available, 0);
+        assert_eq!(state.held, 0);
         assert!(state.locked);
 
-        // Chargeback withdrawal
+        // Charging back a disputed withdrawal would drive total negative, so the
+        // invariant check rejects it and rolls the operation back.
         state.locked = false;
-        let _ = state.deposit(3, 50.0);
-        let _ = state.withdraw(4, 50.0);
+        let _ = state.deposit(3, 500_000);
+        let _ = state.withdraw(4, 500_000);
         let _ = state.dispute(4);
+        assert_eq!(state.available, -500_000);
+        assert_eq!(state.held, 500_000);
         let result = state.chargeback(4);
-        assert!(result.is_ok());
-        assert_eq!(state.available, -50.0);
-        assert_eq!(state.held, 0.0);
-        assert!(state.locked);
+        assert!(result.is_err());
+        assert_eq!(state.available, -500_000);
+        assert_eq!(state.held, 500_000);
+        assert!(!state.locked);
+    }
+
+    #[test]
+    fn test_deposit_only_policy_and_invariants() {
+        // Default policy only permits disputing deposits.
+        let mut state = ClientAccountState::default();
+        let _ = state.deposit(1, 500_000);
+        let _ = state.withdraw(2, 500_000);
+
+        // Disputing a withdrawal is rejected and leaves balances untouched.
+        let result = state.dispute(2);
+        assert!(result.is_err());
+        assert_eq!(state.available, 0);
+        assert_eq!(state.held, 0);
+        assert_eq!(state.txs.get(&2).unwrap().state, TxState::Processed);
+
+        // Disputing the deposit still works.
+        assert!(state.dispute(1).is_ok());
+        assert_eq!(state.held, 500_000);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_illegal_transitions() {
+        let mut state = ClientAccountState::default();
+        let _ = state.deposit(1, 500_000);
+
+        // Disputing an already-disputed tx should fail
+        assert!(state.dispute(1).is_ok());
+        assert!(state.dispute(1).is_err());
+        assert_eq!(state.txs.get(&1).unwrap().state, TxState::Disputed);
+
+        // Re-disputing a resolved tx should fail
+        assert!(state.resolve(1).is_ok());
+        assert_eq!(state.txs.get(&1).unwrap().state, TxState::Resolved);
+        assert!(state.dispute(1).is_err());
+        // ...and it cannot be resolved again
+        assert!(state.resolve(1).is_err());
+
+        // A charged-back tx is terminal: no further transitions
+        let _ = state.deposit(2, 500_000);
+        assert!(state.dispute(2).is_ok());
+        assert!(state.chargeback(2).is_ok());
+        assert_eq!(state.txs.get(&2).unwrap().state, TxState::ChargedBack);
+        assert!(state.chargeback(2).is_err());
+        assert!(state.dispute(2).is_err());
+        assert!(state.resolve(2).is_err());
+    }
+}
@@ -5,14 +5,14 @@ pub struct Transaction {
     pub r#type: TransactionType,
     pub client: u16,
     pub tx: u32,
-    pub amount: Option<f32>,
+    pub amount: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct Output {
     pub client: u16,
-    pub available: f32,
-    pub held: f32,
-    pub total: f32,
+    pub available: String,
+    pub held: String,
+    pub total: String,
     pub locked: bool,
 }
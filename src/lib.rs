@@ -0,0 +1,281 @@
+pub mod client_account_state;
+pub mod precision;
+pub mod io_data;
+pub mod transaction_type;
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::mpsc::sync_channel;
+use std::thread;
+use csv::{ReaderBuilder, Trim};
+use thiserror::Error;
+use io_data::{Output, Transaction};
+use precision::{format_amount, parse_amount};
+use client_account_state::{ClientAccountState, LedgerConfig};
+use transaction_type::TransactionType::{CHARGEBACK, DEPOSIT, DISPUTE, RESOLVE, WITHDRAW};
+
+extern crate csv;
+#[macro_use]
+extern crate serde_derive;
+
+/// Typed failure surfaced by a single ledger operation. Individual transaction
+/// failures are expected during normal processing and do not abort the run;
+/// they are returned so callers can match on the cause instead of parsing
+/// strings.
+#[derive(Debug, Error, PartialEq)]
+pub enum LedgerError {
+    #[error("insufficient funds")]
+    NotEnoughFunds,
+    #[error("no transaction {tx} for client {client}")]
+    UnknownTx { client: u16, tx: u32 },
+    #[error("transaction is not in a disputable state")]
+    AlreadyDisputed,
+    #[error("transaction is not under dispute")]
+    NotDisputed,
+    #[error("account is frozen")]
+    FrozenAccount,
+    #[error("amount must be a positive, non-zero value")]
+    NonPositiveAmount,
+    #[error("this transaction type may not be disputed under the active policy")]
+    DisputeNotAllowed,
+}
+
+/// Top-level failure from [`process`] or [`write_output`]. Wraps the I/O and
+/// CSV errors that genuinely abort a run, as well as ledger errors for
+/// consumers that choose to propagate them.
+#[derive(Debug, Error)]
+pub enum EngineError {
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Ledger(#[from] LedgerError),
+}
+
+/// Stream transactions from `reader`, applying each to the appropriate client
+/// account, and return the resulting account map. Malformed amounts and failed
+/// ledger operations are skipped; only CSV/IO errors abort the run.
+pub fn process<R: Read>(reader: R) -> Result<HashMap<u16, ClientAccountState>, EngineError> {
+    // Initialize the reader and record shape
+    let mut rdr = ReaderBuilder::new().trim(Trim::All).from_reader(reader);
+    let mut raw_record = csv::ByteRecord::new();
+    let headers = rdr.byte_headers()?.clone();
+
+    // Initialize our in-memory account states
+    let mut client_account_states = HashMap::<u16, ClientAccountState>::new();
+    let config = LedgerConfig::default();
+
+    // Iterate over each row one by one
+    while rdr.read_byte_record(&mut raw_record)? { // Performance adjustments made following this: https://docs.rs/csv/latest/csv/tutorial/index.html#performance
+
+        // Attempt a deserialization
+        let record: Transaction = raw_record.deserialize(Some(&headers))?;
+
+        // Fetch the client by id, or create the account state if it doesn't exist
+        let state = client_account_states
+            .entry(record.client)
+            .or_insert_with(|| ClientAccountState::new(record.client, config));
+
+        apply(state, &record);
+    }
+
+    Ok(client_account_states)
+}
+
+/// Like [`process`], but additionally returns the running "total issuance"
+/// accumulator — the sum of all successfully applied deposits minus withdrawals
+/// minus charged-back amounts. See [`verify_conservation`].
+pub fn process_audited<R: Read>(
+    reader: R,
+) -> Result<(HashMap<u16, ClientAccountState>, i64), EngineError> {
+    let mut rdr = ReaderBuilder::new().trim(Trim::All).from_reader(reader);
+    let mut raw_record = csv::ByteRecord::new();
+    let headers = rdr.byte_headers()?.clone();
+
+    let mut client_account_states = HashMap::<u16, ClientAccountState>::new();
+    let config = LedgerConfig::default();
+    let mut issuance: i64 = 0;
+
+    while rdr.read_byte_record(&mut raw_record)? {
+        let record: Transaction = raw_record.deserialize(Some(&headers))?;
+        let state = client_account_states
+            .entry(record.client)
+            .or_insert_with(|| ClientAccountState::new(record.client, config));
+        issuance += apply(state, &record);
+    }
+
+    Ok((client_account_states, issuance))
+}
+
+/// Apply a single deserialized transaction to its client's account state and
+/// return its signed effect on total issuance. Errors are ignored (contributing
+/// zero); failed transactions should not crash the system.
+fn apply(state: &mut ClientAccountState, record: &Transaction) -> i64 {
+    // Match on each transaction type and pass on relevant data the corresponding state handler
+    match record.r#type {
+        DEPOSIT => record.amount.as_deref().and_then(|a| parse_amount(a).ok()).and_then(|amount| state.deposit(record.tx, amount).ok()).unwrap_or(0),
+        WITHDRAW => record.amount.as_deref().and_then(|a| parse_amount(a).ok()).and_then(|amount| state.withdraw(record.tx, amount).ok()).unwrap_or(0),
+        DISPUTE => state.dispute(record.tx).unwrap_or(0),
+        RESOLVE => state.resolve(record.tx).unwrap_or(0),
+        CHARGEBACK => state.chargeback(record.tx).unwrap_or(0),
+    }
+}
+
+/// Conservation-of-funds audit: assert the independently tracked `issuance`
+/// figure equals the sum of every client's `total` (available + held). A
+/// mismatch means some operation failed to conserve funds; the discrepancy is
+/// returned for reporting.
+pub fn verify_conservation(
+    accounts: &HashMap<u16, ClientAccountState>,
+    issuance: i64,
+) -> Result<(), String> {
+    let total: i64 = accounts.values().map(|s| s.available + s.held).sum();
+    if total != issuance {
+        return Err(format!(
+            "conservation check failed: issuance {} != client totals {} (discrepancy {})",
+            format_amount(issuance),
+            format_amount(total),
+            format_amount(issuance - total),
+        ));
+    }
+    Ok(())
+}
+
+/// Like [`process`], but shards work across `threads` worker threads keyed by
+/// `client % threads`. Every transaction for a given client is routed to the
+/// same worker, which owns a disjoint partition of the account map and applies
+/// its clients' transactions strictly in arrival order. The caller's thread
+/// stays the sole CSV consumer, preserving per-client input ordering. A
+/// `threads` count of `0` or `1` falls back to the sequential [`process`].
+pub fn process_sharded<R: Read>(
+    reader: R,
+    threads: usize,
+) -> Result<HashMap<u16, ClientAccountState>, EngineError> {
+    if threads <= 1 {
+        return process(reader);
+    }
+
+    let config = LedgerConfig::default();
+
+    // Spin up one worker per shard, each draining a bounded channel and owning
+    // the accounts whose id hashes to its shard.
+    let mut senders = Vec::with_capacity(threads);
+    let mut workers = Vec::with_capacity(threads);
+    for _ in 0..threads {
+        let (tx, rx) = sync_channel::<Transaction>(1024);
+        senders.push(tx);
+        workers.push(thread::spawn(move || {
+            let mut partition = HashMap::<u16, ClientAccountState>::new();
+            for record in rx {
+                let state = partition
+                    .entry(record.client)
+                    .or_insert_with(|| ClientAccountState::new(record.client, config));
+                apply(state, &record);
+            }
+            partition
+        }));
+    }
+
+    // The reader thread stays the sole CSV consumer and dispatches each record
+    // to its shard.
+    let mut rdr = ReaderBuilder::new().trim(Trim::All).from_reader(reader);
+    let mut raw_record = csv::ByteRecord::new();
+    let headers = rdr.byte_headers()?.clone();
+    while rdr.read_byte_record(&mut raw_record)? {
+        let record: Transaction = raw_record.deserialize(Some(&headers))?;
+        let shard = record.client as usize % threads;
+        // A disconnected worker means the thread panicked; surface it on join.
+        if senders[shard].send(record).is_err() {
+            break;
+        }
+    }
+
+    // Drop the senders so the workers observe EOF, then join and merge the
+    // disjoint partitions.
+    drop(senders);
+    let mut client_account_states = HashMap::<u16, ClientAccountState>::new();
+    for worker in workers {
+        let partition = worker.join().expect("worker thread panicked");
+        client_account_states.extend(partition);
+    }
+
+    Ok(client_account_states)
+}
+
+/// Serialize every client's balances to `writer` as CSV.
+pub fn write_output<W: Write>(
+    accounts: &HashMap<u16, ClientAccountState>,
+    writer: W,
+) -> Result<(), EngineError> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    for (client_id, client_state) in accounts {
+        wtr.serialize(Output {
+            client: *client_id,
+            available: format_amount(client_state.available),
+            held: format_amount(client_state.held),
+            total: format_amount(client_state.available + client_state.held),
+            locked: client_state.locked,
+        })?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{process, process_audited, process_sharded, verify_conservation};
+    use std::collections::BTreeMap;
+
+    const INPUT: &str = "\
+type,client,tx,amount
+deposit,1,1,1.0
+deposit,2,2,2.0
+deposit,1,3,2.0
+withdrawal,1,4,1.5
+dispute,1,1,
+deposit,3,5,10.0
+deposit,2,6,5.0
+dispute,2,2,
+resolve,2,2,
+deposit,1,7,3.3333
+chargeback,1,1,
+deposit,3,8,0.0001
+";
+
+    /// Normalize a result map into an order-independent comparable form.
+    fn snapshot(
+        accounts: &std::collections::HashMap<u16, super::ClientAccountState>,
+    ) -> BTreeMap<u16, (i64, i64, bool)> {
+        accounts
+            .iter()
+            .map(|(id, s)| (*id, (s.available, s.held, s.locked)))
+            .collect()
+    }
+
+    #[test]
+    fn test_sharded_matches_sequential() {
+        let sequential = process(INPUT.as_bytes()).unwrap();
+        for threads in [2, 3, 4, 8] {
+            let sharded = process_sharded(INPUT.as_bytes(), threads).unwrap();
+            assert_eq!(
+                snapshot(&sequential),
+                snapshot(&sharded),
+                "sharded output with {} threads diverged from sequential",
+                threads
+            );
+        }
+    }
+
+    #[test]
+    fn test_conservation_holds() {
+        let (accounts, issuance) = process_audited(INPUT.as_bytes()).unwrap();
+        // The independently tracked issuance must equal the sum of client totals.
+        let total: i64 = accounts.values().map(|s| s.available + s.held).sum();
+        assert_eq!(issuance, total);
+        assert!(verify_conservation(&accounts, issuance).is_ok());
+
+        // A tampered figure must be reported as a discrepancy.
+        assert!(verify_conservation(&accounts, issuance + 1).is_err());
+    }
+}
@@ -1,25 +1,9 @@
-mod client_account_state;
-mod precision;
-mod io_data;
-mod transaction_type;
-
-use std::error::Error;
+use std::fs::File;
 use std::{env, io, process};
-use std::collections::HashMap;
-use csv::{ReaderBuilder, Trim};
-use io_data::{Output, Transaction};
-use precision::convert_precision;
-use crate::client_account_state::ClientAccountState;
-use transaction_type::TransactionType::{CHARGEBACK, DEPOSIT, DISPUTE, RESOLVE, WITHDRAW};
-
-
-extern crate csv;
-#[macro_use]
-extern crate serde_derive;
+use simple_toy_payment_engine::{process_audited, process_sharded, verify_conservation, write_output};
 
-
-fn run() -> Result<(), Box<dyn Error>> {
-    // Read the input filepath
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    // Read the input filepath and optional --threads flag
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
         eprintln!("Must provide input file.");
@@ -27,66 +11,44 @@ fn run() -> Result<(), Box<dyn Error>> {
     }
     let input_filepath = &args[1]; // input file, ie. transactions.csv
 
-    // Initialize the reader and record shape
-    let mut rdr = ReaderBuilder::new().trim(Trim::All).from_path(input_filepath)?;
-    let mut raw_record = csv::ByteRecord::new();
-    let headers = rdr.byte_headers()?.clone();
-
-    // Initialize our in-memory account states
-    let mut client_account_states = HashMap::<u16, ClientAccountState>::new();
-
-    // Iterate over each row one by one
-    while rdr.read_byte_record(&mut raw_record)? { // Performance adjustments made following this: https://docs.rs/csv/latest/csv/tutorial/index.html#performance
-
-        // Attempt a deserialization
-        let record: Transaction = raw_record.deserialize(Some(&headers))?;
-
-        // Fetch the client by id, or create the account state if it doesn't exist
-        let state = client_account_states.entry(record.client).or_default();
-
-        // In the case of locked accounts, we will skip the transaction
-        if state.locked { continue; }
-
-        // Match on each transaction type and pass on relevant data the corresponding state handler
-        // Errors are ignored; failed transactions should not crash the system
-        match record.r#type {
-            DEPOSIT => { if let Some(amount) = record.amount { let _ = state.deposit(record.tx, amount); } }
-            WITHDRAW => { if let Some(amount) = record.amount { let _ = state.withdraw(record.tx, amount); } }
-            DISPUTE => { let _ = state.dispute(record.tx); }
-            RESOLVE => { let _ = state.resolve(record.tx); }
-            CHARGEBACK => { let _ = state.chargeback(record.tx); }
+    // Parse the optional `--threads N` and `--verify` flags; the sequential path
+    // remains the default so existing behavior and its determinism are unchanged.
+    let mut threads = 1usize;
+    let mut verify = false;
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--threads" => {
+                if let Some(value) = rest.next() {
+                    threads = value.parse()?;
+                }
+            }
+            "--verify" => verify = true,
+            _ => {}
         }
     }
 
-    // Initialize the output writer to std output
+    // Stream the transactions through the engine and pipe the balances to std output.
     // If a > $file argument is provided it should pipe std output to that file.
-    let mut wtr = csv::Writer::from_writer(io::stdout());
-
-    // Iterate over all accounts store in the state and write it to the file
-    for (client_id, client_state) in &client_account_states {
-        let available = convert_precision(client_state.available);
-        let held = convert_precision(client_state.held);
-        wtr.serialize(Output {
-            client: *client_id,
-            available,
-            held,
-            total: available + held,
-            locked: client_state.locked,
-        })?;
+    if verify {
+        // The conservation audit runs on the sequential path so it can track the
+        // single total-issuance accumulator alongside processing.
+        let (accounts, issuance) = process_audited(File::open(input_filepath)?)?;
+        write_output(&accounts, io::stdout())?;
+        if let Err(discrepancy) = verify_conservation(&accounts, issuance) {
+            eprintln!("{}", discrepancy);
+            process::exit(1);
+        }
+    } else {
+        let accounts = process_sharded(File::open(input_filepath)?, threads)?;
+        write_output(&accounts, io::stdout())?;
     }
-
-    wtr.flush()?;
     Ok(())
 }
 
-#[allow(unused_must_use)]
 fn main() {
     if let Err(err) = run() {
         eprintln!("error: {}", err);
         process::exit(1)
     }
 }
-
-
-
-
@@ -0,0 +1,110 @@
+/// Every monetary amount is represented as an `i64` holding the value scaled
+/// by `SCALE`, giving exactly four decimal places of precision without any of
+/// the rounding drift that floating point would introduce.
+pub const SCALE: i64 = 10_000;
+
+/// Parse a CSV `amount` field into a fixed-point integer scaled by [`SCALE`].
+///
+/// The field is split on the decimal point; at most four fractional digits are
+/// permitted and anything finer is rejected (e.g. `0.0000000000001`). So
+/// `1.2345` becomes `12345` and `5` becomes `50000`.
+pub fn parse_amount(raw: &str) -> Result<i64, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Err(String::from("Empty amount"));
+    }
+
+    let (negative, body) = match raw.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, raw.strip_prefix('+').unwrap_or(raw)),
+    };
+
+    let mut parts = body.splitn(2, '.');
+    let int_part = parts.next().unwrap_or("");
+    let frac_part = parts.next().unwrap_or("");
+
+    if frac_part.len() > 4 {
+        return Err(format!("Amount {} has more than four decimal places", raw));
+    }
+    if int_part.is_empty() && frac_part.is_empty() {
+        return Err(format!("Amount {} is not a valid decimal", raw));
+    }
+    if !int_part.bytes().all(|b| b.is_ascii_digit())
+        || !frac_part.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(format!("Amount {} is not a valid decimal", raw));
+    }
+
+    let int_value: i64 = if int_part.is_empty() {
+        0
+    } else {
+        int_part
+            .parse()
+            .map_err(|_| format!("Amount {} is out of range", raw))?
+    };
+
+    // Right-pad the fractional digits to exactly four places so `1.5` scales the
+    // same way as `1.5000`.
+    let frac_value: i64 = format!("{:0<4}", frac_part)
+        .parse()
+        .map_err(|_| format!("Amount {} is out of range", raw))?;
+
+    let scaled = int_value
+        .checked_mul(SCALE)
+        .and_then(|v| v.checked_add(frac_value))
+        .ok_or_else(|| format!("Amount {} overflows", raw))?;
+
+    Ok(if negative { -scaled } else { scaled })
+}
+
+/// Format a fixed-point integer back into a decimal string, printing the
+/// fractional remainder as zero-padded digits with trailing zeros trimmed.
+pub fn format_amount(value: i64) -> String {
+    let negative = value < 0;
+    let magnitude = value.unsigned_abs();
+    let scale = SCALE as u64;
+    let int_part = magnitude / scale;
+    let frac_part = magnitude % scale;
+
+    let mut formatted = if frac_part == 0 {
+        int_part.to_string()
+    } else {
+        let frac = format!("{:04}", frac_part);
+        format!("{}.{}", int_part, frac.trim_end_matches('0'))
+    };
+
+    if negative {
+        formatted.insert(0, '-');
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::precision::{format_amount, parse_amount};
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("1.2345"), Ok(12345));
+        assert_eq!(parse_amount("5"), Ok(50000));
+        assert_eq!(parse_amount("0.0001"), Ok(1));
+        assert_eq!(parse_amount("-30"), Ok(-300000));
+
+        // More than four fractional digits is rejected.
+        assert!(parse_amount("0.0000000000001").is_err());
+        assert!(parse_amount("0.00001").is_err());
+
+        // Garbage input is rejected.
+        assert!(parse_amount("").is_err());
+        assert!(parse_amount("abc").is_err());
+    }
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(format_amount(12345), "1.2345");
+        assert_eq!(format_amount(50000), "5");
+        assert_eq!(format_amount(1), "0.0001");
+        assert_eq!(format_amount(0), "0");
+        assert_eq!(format_amount(-500000), "-50");
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
 pub enum TransactionType {
     #[serde(alias = "deposit")]
     DEPOSIT,